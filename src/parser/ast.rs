@@ -1,16 +1,29 @@
 
+use std::any::Any;
 use std::fmt;
+use std::mem;
 
 use parser::ast_builder::FullToken;
+use parser::pretty_print::{Breaks, PrettyPrinter};
 use parser::tokenizer::Token;
 
-#[derive(Debug, Clone, Copy)]
+fn token_text(token: &FullToken) -> String {
+    let mut text = String::new();
+    token.write_token(&mut text).unwrap();
+    text
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AstKind {
     Program,
     BlockStmt,
     VarStmt,
     EmptyStmt,
     IfStmt,
+    WhileStmt,
+    ForStmt,
+    FnStmt,
+    ReturnStmt,
     ExprStmt,
 
     BinaryOpExpr,
@@ -25,17 +38,71 @@ impl AstKind {
     }
 }
 
+/*****************************************************************************
+ **** Span ********************************************************************
+ *****************************************************************************/
+/// A half-open byte range `[start, end)` into the `VecInputStream` backing
+/// the parse, recording where an `AstNode` came from in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    start: usize,
+    end: usize
+}
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        assert!(start <= end);
+        Span { start, end }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
 pub trait AstNode where Self: fmt::Debug {
     fn kind(&self) -> AstKind;
+    fn span(&self) -> Span;
     fn is_statement(&self) -> bool;
     fn is_expression(&self) -> bool;
     fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error>;
 
+    /// Reconstructs this node as syntactically valid, indented JavaScript
+    /// by feeding `p` the node's logical groups and breaks, unlike
+    /// `write_tree`'s debug dump.
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error>;
+
+    /// Exposes `self` as `Any` so that [`cast`](../cast/fn.cast.html) can
+    /// recover the concrete node type behind a `&dyn AstNode`.
+    fn as_any(&self) -> &Any;
+
     fn tree_string(&self) -> String {
         let mut str = String::new();
         self.write_tree(&mut str).unwrap();
         str
     }
+
+    fn source_string(&self) -> String {
+        let mut printer = PrettyPrinter::new(0, 80);
+        self.write_source(&mut printer).unwrap();
+        printer.finish()
+    }
+}
+
+/// Implemented by every concrete node type, associating it with the
+/// `AstKind` variant that [`AstNode::kind`] reports for it. This lets
+/// [`cast`](../cast/fn.cast.html) check the kind before downcasting,
+/// without requiring an instance of the target type in hand.
+pub trait AstNodeKind: AstNode + Sized + 'static {
+    const KIND: AstKind;
 }
 
 /*****************************************************************************
@@ -43,11 +110,12 @@ pub trait AstNode where Self: fmt::Debug {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct ProgramNode {
+    span: Span,
     source_elements: Vec<Box<AstNode>>
 }
 impl ProgramNode {
-    pub fn new() -> ProgramNode {
-        ProgramNode { source_elements: Vec::with_capacity(3) }
+    pub fn new(span: Span) -> ProgramNode {
+        ProgramNode { span, source_elements: Vec::with_capacity(3) }
     }
 
     pub fn source_elements(&self) -> &Vec<Box<AstNode>> {
@@ -56,11 +124,25 @@ impl ProgramNode {
     pub fn add_source_element(&mut self, source_element: Box<AstNode>) {
         self.source_elements.push(source_element);
     }
+
+    /// Splices `source_element` in at `index`, returning the node that
+    /// was there before so a rewriting pass can inspect or discard it.
+    pub fn replace_source_element(&mut self, index: usize, source_element: Box<AstNode>)
+        -> Box<AstNode>
+    {
+        mem::replace(&mut self.source_elements[index], source_element)
+    }
 }
 impl AstNode for ProgramNode {
     fn kind(&self) -> AstKind {
         AstKind::Program
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         false
     }
@@ -81,6 +163,27 @@ impl AstNode for ProgramNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.open_box(0, Breaks::Consistent);
+        let mut first = true;
+        for source_element in &self.source_elements {
+            if ! first {
+                // A top-level statement list must always land on
+                // separate lines -- a fit-dependent `brk` would collapse
+                // onto one line (and lose its separator entirely at
+                // blank_space 0) whenever the whole program fits in
+                // max_width.
+                p.hardbreak();
+            }
+            first = false;
+            source_element.write_source(p)?;
+        }
+        p.close_box();
+        Ok(())
+    }
+}
+impl AstNodeKind for ProgramNode {
+    const KIND: AstKind = AstKind::Program;
 }
 
 /*****************************************************************************
@@ -88,16 +191,32 @@ impl AstNode for ProgramNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct BlockStmtNode {
+    span: Span,
+    statements: Vec<Box<AstNode>>
 }
 impl BlockStmtNode {
-    pub fn new() -> BlockStmtNode {
-        BlockStmtNode {}
+    pub fn new(span: Span) -> BlockStmtNode {
+        BlockStmtNode { span, statements: Vec::new() }
+    }
+
+    pub fn statements(&self) -> &Vec<Box<AstNode>> {
+        &self.statements
+    }
+    pub fn add_statement(&mut self, statement: Box<AstNode>) {
+        assert!(statement.is_statement());
+        self.statements.push(statement);
     }
 }
 impl AstNode for BlockStmtNode {
     fn kind(&self) -> AstKind {
         AstKind::BlockStmt
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         true
     }
@@ -105,20 +224,49 @@ impl AstNode for BlockStmtNode {
         false
     }
     fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
-        w.write_str("Block{}")
+        w.write_str("Block{")?;
+        let mut first = true;
+        for statement in &self.statements {
+            if ! first {
+                w.write_str(", ")?;
+            }
+            first = false;
+            statement.write_tree(w)?;
+        }
+        w.write_str("}")
+    }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        if self.statements.is_empty() {
+            p.string("{}");
+            return Ok(());
+        }
+        p.string("{");
+        p.open_box(4, Breaks::Consistent);
+        for statement in &self.statements {
+            p.hardbreak();
+            statement.write_source(p)?;
+        }
+        p.close_box();
+        p.hardbreak();
+        p.string("}");
+        Ok(())
     }
 }
+impl AstNodeKind for BlockStmtNode {
+    const KIND: AstKind = AstKind::BlockStmt;
+}
 
 /*****************************************************************************
  **** VarStmtNode ************************************************************
  *****************************************************************************/
 #[derive(Debug)]
 pub struct VarStmtNode {
+    span: Span,
     variables: Vec<Box<FullToken>>
 }
 impl VarStmtNode {
-    pub fn new() -> VarStmtNode {
-        VarStmtNode { variables: Vec::with_capacity(1) }
+    pub fn new(span: Span) -> VarStmtNode {
+        VarStmtNode { span, variables: Vec::with_capacity(1) }
     }
 
     pub fn variables(&self) -> &Vec<Box<FullToken>> {
@@ -132,6 +280,12 @@ impl AstNode for VarStmtNode {
     fn kind(&self) -> AstKind {
         AstKind::VarStmt
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         true
     }
@@ -152,6 +306,25 @@ impl AstNode for VarStmtNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.open_box(0, Breaks::Inconsistent);
+        p.string("var ");
+        let mut first = true;
+        for variable in &self.variables {
+            if ! first {
+                p.string(",");
+                p.brk(0, 1);
+            }
+            first = false;
+            p.string(&token_text(variable));
+        }
+        p.string(";");
+        p.close_box();
+        Ok(())
+    }
+}
+impl AstNodeKind for VarStmtNode {
+    const KIND: AstKind = AstKind::VarStmt;
 }
 
 /*****************************************************************************
@@ -159,16 +332,23 @@ impl AstNode for VarStmtNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct EmptyStmtNode {
+    span: Span
 }
 impl EmptyStmtNode {
-    pub fn new() -> EmptyStmtNode {
-        EmptyStmtNode {}
+    pub fn new(span: Span) -> EmptyStmtNode {
+        EmptyStmtNode { span }
     }
 }
 impl AstNode for EmptyStmtNode {
     fn kind(&self) -> AstKind {
         AstKind::EmptyStmt
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         true
     }
@@ -179,6 +359,13 @@ impl AstNode for EmptyStmtNode {
     fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
         w.write_str("Empty{}")
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string(";");
+        Ok(())
+    }
+}
+impl AstNodeKind for EmptyStmtNode {
+    const KIND: AstKind = AstKind::EmptyStmt;
 }
 
 /*****************************************************************************
@@ -186,14 +373,21 @@ impl AstNode for EmptyStmtNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct IfStmtNode {
+    span: Span,
     cond_expr: Box<AstNode>,
-    if_true_stmt: Box<AstNode>
+    if_true_stmt: Box<AstNode>,
+    else_stmt: Option<Box<AstNode>>
 }
 impl IfStmtNode {
-    pub fn new_if(cond_expr: Box<AstNode>, if_true_stmt: Box<AstNode>)
+    pub fn new_if(span: Span, cond_expr: Box<AstNode>, if_true_stmt: Box<AstNode>)
         -> IfStmtNode
     {
-        IfStmtNode { cond_expr, if_true_stmt }
+        IfStmtNode { span, cond_expr, if_true_stmt, else_stmt: None }
+    }
+    pub fn new_if_else(span: Span, cond_expr: Box<AstNode>, if_true_stmt: Box<AstNode>,
+        else_stmt: Box<AstNode>) -> IfStmtNode
+    {
+        IfStmtNode { span, cond_expr, if_true_stmt, else_stmt: Some(else_stmt) }
     }
 
     pub fn cond_expr(&self) -> &AstNode {
@@ -202,11 +396,35 @@ impl IfStmtNode {
     pub fn if_true_stmt(&self) -> &AstNode {
         self.if_true_stmt.as_ref()
     }
+    pub fn else_stmt(&self) -> Option<&AstNode> {
+        self.else_stmt.as_ref().map(|node| node.as_ref())
+    }
+
+    /// Replaces the condition expression in place. The caller is
+    /// responsible for the replacement satisfying `is_expression()`.
+    pub fn set_cond_expr(&mut self, cond_expr: Box<AstNode>) {
+        assert!(cond_expr.is_expression());
+        self.cond_expr = cond_expr;
+    }
+    /// Replaces the `if`-true branch in place.
+    pub fn set_if_true_stmt(&mut self, if_true_stmt: Box<AstNode>) {
+        self.if_true_stmt = if_true_stmt;
+    }
+    /// Sets or clears the `else` branch in place.
+    pub fn set_else_stmt(&mut self, else_stmt: Option<Box<AstNode>>) {
+        self.else_stmt = else_stmt;
+    }
 }
 impl AstNode for IfStmtNode {
     fn kind(&self) -> AstKind {
         AstKind::IfStmt
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         true
     }
@@ -220,21 +438,353 @@ impl AstNode for IfStmtNode {
         w.write_str("){")?;
         self.if_true_stmt.write_tree(w)?;
         w.write_str("}")?;
+        if let Some(ref else_stmt) = self.else_stmt {
+            w.write_str("Else{")?;
+            else_stmt.write_tree(w)?;
+            w.write_str("}")?;
+        }
+        Ok(())
+    }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string("if (");
+        self.cond_expr.write_source(p)?;
+        p.string(") ");
+        self.if_true_stmt.write_source(p)?;
+        if let Some(ref else_stmt) = self.else_stmt {
+            p.string(" else ");
+            else_stmt.write_source(p)?;
+        }
         Ok(())
     }
 }
+impl AstNodeKind for IfStmtNode {
+    const KIND: AstKind = AstKind::IfStmt;
+}
+
+/*****************************************************************************
+ **** WhileStmtNode **********************************************************
+ *****************************************************************************/
+#[derive(Debug)]
+pub struct WhileStmtNode {
+    span: Span,
+    cond_expr: Box<AstNode>,
+    body: Box<AstNode>
+}
+impl WhileStmtNode {
+    pub fn new(span: Span, cond_expr: Box<AstNode>, body: Box<AstNode>) -> WhileStmtNode {
+        assert!(cond_expr.is_expression());
+        WhileStmtNode { span, cond_expr, body }
+    }
+
+    pub fn cond_expr(&self) -> &AstNode {
+        self.cond_expr.as_ref()
+    }
+    pub fn body(&self) -> &AstNode {
+        self.body.as_ref()
+    }
+
+    pub fn set_cond_expr(&mut self, cond_expr: Box<AstNode>) {
+        assert!(cond_expr.is_expression());
+        self.cond_expr = cond_expr;
+    }
+    pub fn set_body(&mut self, body: Box<AstNode>) {
+        self.body = body;
+    }
+}
+impl AstNode for WhileStmtNode {
+    fn kind(&self) -> AstKind {
+        AstKind::WhileStmt
+    }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn is_statement(&self) -> bool {
+        true
+    }
+    fn is_expression(&self) -> bool {
+        false
+    }
+
+    fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
+        w.write_str("While(")?;
+        self.cond_expr.write_tree(w)?;
+        w.write_str("){")?;
+        self.body.write_tree(w)?;
+        w.write_str("}")?;
+        Ok(())
+    }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string("while (");
+        self.cond_expr.write_source(p)?;
+        p.string(") ");
+        self.body.write_source(p)?;
+        Ok(())
+    }
+}
+impl AstNodeKind for WhileStmtNode {
+    const KIND: AstKind = AstKind::WhileStmt;
+}
+
+/*****************************************************************************
+ **** ForStmtNode ************************************************************
+ *****************************************************************************/
+#[derive(Debug)]
+pub struct ForStmtNode {
+    span: Span,
+    setup: Option<Box<AstNode>>,
+    condition: Option<Box<AstNode>>,
+    exec: Option<Box<AstNode>>,
+    body: Box<AstNode>
+}
+impl ForStmtNode {
+    pub fn new(span: Span, setup: Option<Box<AstNode>>, condition: Option<Box<AstNode>>,
+        exec: Option<Box<AstNode>>, body: Box<AstNode>) -> ForStmtNode
+    {
+        if let Some(ref condition) = condition {
+            assert!(condition.is_expression());
+        }
+        if let Some(ref exec) = exec {
+            assert!(exec.is_expression());
+        }
+        ForStmtNode { span, setup, condition, exec, body }
+    }
+
+    pub fn setup(&self) -> Option<&AstNode> {
+        self.setup.as_ref().map(|node| node.as_ref())
+    }
+    pub fn condition(&self) -> Option<&AstNode> {
+        self.condition.as_ref().map(|node| node.as_ref())
+    }
+    pub fn exec(&self) -> Option<&AstNode> {
+        self.exec.as_ref().map(|node| node.as_ref())
+    }
+    pub fn body(&self) -> &AstNode {
+        self.body.as_ref()
+    }
+
+    pub fn set_body(&mut self, body: Box<AstNode>) {
+        self.body = body;
+    }
+}
+impl AstNode for ForStmtNode {
+    fn kind(&self) -> AstKind {
+        AstKind::ForStmt
+    }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn is_statement(&self) -> bool {
+        true
+    }
+    fn is_expression(&self) -> bool {
+        false
+    }
+
+    fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
+        w.write_str("For(")?;
+        if let Some(ref setup) = self.setup {
+            setup.write_tree(w)?;
+        }
+        w.write_str(";")?;
+        if let Some(ref condition) = self.condition {
+            condition.write_tree(w)?;
+        }
+        w.write_str(";")?;
+        if let Some(ref exec) = self.exec {
+            exec.write_tree(w)?;
+        }
+        w.write_str("){")?;
+        self.body.write_tree(w)?;
+        w.write_str("}")?;
+        Ok(())
+    }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string("for (");
+        // `setup` is a statement (`ExprStmtNode`/`VarStmtNode`) and so
+        // already emits its own trailing ";" -- only supply one here
+        // when there's no setup to provide it. Likewise `condition` and
+        // `exec` only get a separating space when they're actually
+        // present, so an empty clause (e.g. `for (;;)`) doesn't leave a
+        // stray space behind.
+        match self.setup {
+            Some(ref setup) => setup.write_source(p)?,
+            None => p.string(";")
+        }
+        if let Some(ref condition) = self.condition {
+            p.string(" ");
+            condition.write_source(p)?;
+        }
+        p.string(";");
+        if let Some(ref exec) = self.exec {
+            p.string(" ");
+            exec.write_source(p)?;
+        }
+        p.string(") ");
+        self.body.write_source(p)?;
+        Ok(())
+    }
+}
+impl AstNodeKind for ForStmtNode {
+    const KIND: AstKind = AstKind::ForStmt;
+}
+
+/*****************************************************************************
+ **** FunctionDeclNode *******************************************************
+ *****************************************************************************/
+#[derive(Debug)]
+pub struct FunctionDeclNode {
+    span: Span,
+    name: FullToken,
+    params: Vec<Box<FullToken>>,
+    body: Box<AstNode>
+}
+impl FunctionDeclNode {
+    pub fn new(span: Span, name: FullToken, body: Box<AstNode>) -> FunctionDeclNode {
+        assert!(name.kind().is_identifier());
+        FunctionDeclNode { span, name, params: Vec::with_capacity(2), body }
+    }
+
+    pub fn name(&self) -> &FullToken {
+        &self.name
+    }
+    pub fn params(&self) -> &Vec<Box<FullToken>> {
+        &self.params
+    }
+    pub fn add_param(&mut self, param: FullToken) {
+        assert!(param.kind().is_identifier());
+        self.params.push(Box::new(param));
+    }
+    pub fn body(&self) -> &AstNode {
+        self.body.as_ref()
+    }
+}
+impl AstNode for FunctionDeclNode {
+    fn kind(&self) -> AstKind {
+        AstKind::FnStmt
+    }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn is_statement(&self) -> bool {
+        true
+    }
+    fn is_expression(&self) -> bool {
+        false
+    }
+
+    fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
+        w.write_str("Fn(")?;
+        self.name.write_token(w)?;
+        for param in &self.params {
+            w.write_str(", ")?;
+            param.write_token(w)?;
+        }
+        w.write_str("){")?;
+        self.body.write_tree(w)?;
+        w.write_str("}")?;
+        Ok(())
+    }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string("function ");
+        p.string(&token_text(&self.name));
+        p.string("(");
+        let mut first = true;
+        for param in &self.params {
+            if ! first {
+                p.string(", ");
+            }
+            first = false;
+            p.string(&token_text(param));
+        }
+        p.string(") ");
+        self.body.write_source(p)?;
+        Ok(())
+    }
+}
+impl AstNodeKind for FunctionDeclNode {
+    const KIND: AstKind = AstKind::FnStmt;
+}
+
+/*****************************************************************************
+ **** ReturnStmtNode *********************************************************
+ *****************************************************************************/
+#[derive(Debug)]
+pub struct ReturnStmtNode {
+    span: Span,
+    expr: Option<Box<AstNode>>
+}
+impl ReturnStmtNode {
+    pub fn new(span: Span, expr: Option<Box<AstNode>>) -> ReturnStmtNode {
+        if let Some(ref expr) = expr {
+            assert!(expr.is_expression());
+        }
+        ReturnStmtNode { span, expr }
+    }
+
+    pub fn expression(&self) -> Option<&AstNode> {
+        self.expr.as_ref().map(|node| node.as_ref())
+    }
+}
+impl AstNode for ReturnStmtNode {
+    fn kind(&self) -> AstKind {
+        AstKind::ReturnStmt
+    }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn is_statement(&self) -> bool {
+        true
+    }
+    fn is_expression(&self) -> bool {
+        false
+    }
+
+    fn write_tree(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
+        w.write_str("Return{")?;
+        if let Some(ref expr) = self.expr {
+            expr.write_tree(w)?;
+        }
+        w.write_str("}")?;
+        Ok(())
+    }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string("return");
+        if let Some(ref expr) = self.expr {
+            p.string(" ");
+            expr.write_source(p)?;
+        }
+        p.string(";");
+        Ok(())
+    }
+}
+impl AstNodeKind for ReturnStmtNode {
+    const KIND: AstKind = AstKind::ReturnStmt;
+}
 
 /*****************************************************************************
  **** ExprStmtNode ***********************************************************
  *****************************************************************************/
 #[derive(Debug)]
 pub struct ExprStmtNode {
+    span: Span,
     expr: Box<AstNode>
 }
 impl ExprStmtNode {
-    pub fn new(expr: Box<AstNode>) -> ExprStmtNode {
+    pub fn new(span: Span, expr: Box<AstNode>) -> ExprStmtNode {
         assert!(expr.is_expression());
-        ExprStmtNode { expr }
+        ExprStmtNode { span, expr }
     }
 
     pub fn expression(&self) -> &AstNode {
@@ -245,6 +795,12 @@ impl AstNode for ExprStmtNode {
     fn kind(&self) -> AstKind {
         AstKind::ExprStmt
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         true
     }
@@ -257,6 +813,14 @@ impl AstNode for ExprStmtNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        self.expr.write_source(p)?;
+        p.string(";");
+        Ok(())
+    }
+}
+impl AstNodeKind for ExprStmtNode {
+    const KIND: AstKind = AstKind::ExprStmt;
 }
 
 /*****************************************************************************
@@ -264,17 +828,18 @@ impl AstNode for ExprStmtNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct BinaryOpExprNode {
+    span: Span,
     binary_op: FullToken,
     left_expr: Box<AstNode>,
     right_expr: Box<AstNode>
 }
 impl BinaryOpExprNode {
-    pub fn new(binary_op: FullToken, left_expr: Box<AstNode>, right_expr: Box<AstNode>)
+    pub fn new(span: Span, binary_op: FullToken, left_expr: Box<AstNode>, right_expr: Box<AstNode>)
         -> BinaryOpExprNode
     {
         assert!(left_expr.is_expression());
         assert!(right_expr.is_expression());
-        BinaryOpExprNode { binary_op, left_expr, right_expr }
+        BinaryOpExprNode { span, binary_op, left_expr, right_expr }
     }
 
     pub fn binary_op(&self) -> &FullToken {
@@ -286,11 +851,28 @@ impl BinaryOpExprNode {
     pub fn right_expr(&self) -> &AstNode {
         self.right_expr.as_ref()
     }
+
+    /// Replaces the left operand in place.
+    pub fn set_left_expr(&mut self, left_expr: Box<AstNode>) {
+        assert!(left_expr.is_expression());
+        self.left_expr = left_expr;
+    }
+    /// Replaces the right operand in place.
+    pub fn set_right_expr(&mut self, right_expr: Box<AstNode>) {
+        assert!(right_expr.is_expression());
+        self.right_expr = right_expr;
+    }
 }
 impl AstNode for BinaryOpExprNode {
     fn kind(&self) -> AstKind {
         AstKind::BinaryOpExpr
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         false
     }
@@ -307,6 +889,19 @@ impl AstNode for BinaryOpExprNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.open_box(0, Breaks::Inconsistent);
+        self.left_expr.write_source(p)?;
+        p.brk(0, 1);
+        p.string(&token_text(&self.binary_op));
+        p.brk(0, 1);
+        self.right_expr.write_source(p)?;
+        p.close_box();
+        Ok(())
+    }
+}
+impl AstNodeKind for BinaryOpExprNode {
+    const KIND: AstKind = AstKind::BinaryOpExpr;
 }
 
 /*****************************************************************************
@@ -314,18 +909,19 @@ impl AstNode for BinaryOpExprNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct CondExprNode {
+    span: Span,
     cond_expr: Box<AstNode>,
     if_expr: Box<AstNode>,
     else_expr: Box<AstNode>
 }
 impl CondExprNode {
-    pub fn new(cond_expr: Box<AstNode>, if_expr: Box<AstNode>, else_expr: Box<AstNode>)
+    pub fn new(span: Span, cond_expr: Box<AstNode>, if_expr: Box<AstNode>, else_expr: Box<AstNode>)
         -> CondExprNode
     {
         assert!(cond_expr.is_expression());
         assert!(if_expr.is_expression());
         assert!(else_expr.is_expression());
-        CondExprNode { cond_expr, if_expr, else_expr }
+        CondExprNode { span, cond_expr, if_expr, else_expr }
     }
 
     pub fn cond_expr(&self) -> &AstNode {
@@ -342,6 +938,12 @@ impl AstNode for CondExprNode {
     fn kind(&self) -> AstKind {
         AstKind::CondExpr
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         false
     }
@@ -358,6 +960,21 @@ impl AstNode for CondExprNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.open_box(0, Breaks::Inconsistent);
+        self.cond_expr.write_source(p)?;
+        p.brk(0, 1);
+        p.string("? ");
+        self.if_expr.write_source(p)?;
+        p.brk(0, 1);
+        p.string(": ");
+        self.else_expr.write_source(p)?;
+        p.close_box();
+        Ok(())
+    }
+}
+impl AstNodeKind for CondExprNode {
+    const KIND: AstKind = AstKind::CondExpr;
 }
 
 /*****************************************************************************
@@ -365,19 +982,20 @@ impl AstNode for CondExprNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct AssignExprNode {
+    span: Span,
     assign_op: FullToken,
     left_expr: Box<AstNode>,
     right_expr: Box<AstNode>
 }
 impl AssignExprNode {
-    pub fn new(assign_op: FullToken, left_expr: Box<AstNode>, right_expr: Box<AstNode>)
+    pub fn new(span: Span, assign_op: FullToken, left_expr: Box<AstNode>, right_expr: Box<AstNode>)
         -> AssignExprNode
     {
         // FIXME: assert that left_expr is a valid lvalue expression.
         assert!(left_expr.is_expression());
         assert!(right_expr.is_expression());
         assert!(assign_op.kind().is_assignment_op());
-        AssignExprNode { assign_op, left_expr, right_expr }
+        AssignExprNode { span, assign_op, left_expr, right_expr }
     }
 
     pub fn assignment_op(&self) -> &FullToken {
@@ -394,6 +1012,12 @@ impl AstNode for AssignExprNode {
     fn kind(&self) -> AstKind {
         AstKind::AssignExpr
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         false
     }
@@ -410,6 +1034,19 @@ impl AstNode for AssignExprNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.open_box(0, Breaks::Inconsistent);
+        self.left_expr.write_source(p)?;
+        p.string(" ");
+        p.string(&token_text(&self.assign_op));
+        p.brk(0, 1);
+        self.right_expr.write_source(p)?;
+        p.close_box();
+        Ok(())
+    }
+}
+impl AstNodeKind for AssignExprNode {
+    const KIND: AstKind = AstKind::AssignExpr;
 }
 
 /*****************************************************************************
@@ -417,14 +1054,15 @@ impl AstNode for AssignExprNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct CommaExprNode {
+    span: Span,
     left_expr: Box<AstNode>,
     right_expr: Box<AstNode>
 }
 impl CommaExprNode {
-    pub fn new(left_expr: Box<AstNode>, right_expr: Box<AstNode>) -> CommaExprNode {
+    pub fn new(span: Span, left_expr: Box<AstNode>, right_expr: Box<AstNode>) -> CommaExprNode {
         assert!(left_expr.is_expression());
         assert!(right_expr.is_expression());
-        CommaExprNode { left_expr, right_expr }
+        CommaExprNode { span, left_expr, right_expr }
     }
 
     pub fn left_expr(&self) -> &AstNode {
@@ -438,6 +1076,12 @@ impl AstNode for CommaExprNode {
     fn kind(&self) -> AstKind {
         AstKind::CommaExpr
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         false
     }
@@ -452,6 +1096,18 @@ impl AstNode for CommaExprNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.open_box(0, Breaks::Inconsistent);
+        self.left_expr.write_source(p)?;
+        p.string(",");
+        p.brk(0, 1);
+        self.right_expr.write_source(p)?;
+        p.close_box();
+        Ok(())
+    }
+}
+impl AstNodeKind for CommaExprNode {
+    const KIND: AstKind = AstKind::CommaExpr;
 }
 
 /*****************************************************************************
@@ -459,12 +1115,13 @@ impl AstNode for CommaExprNode {
  *****************************************************************************/
 #[derive(Debug)]
 pub struct NameExprNode {
+    span: Span,
     name: FullToken
 }
 impl NameExprNode {
-    pub fn new(name: FullToken) -> NameExprNode {
+    pub fn new(span: Span, name: FullToken) -> NameExprNode {
         assert!(name.kind().is_identifier());
-        NameExprNode { name }
+        NameExprNode { span, name }
     }
 
     pub fn name(&self) -> &FullToken {
@@ -475,6 +1132,12 @@ impl AstNode for NameExprNode {
     fn kind(&self) -> AstKind {
         AstKind::NameExpr
     }
+    fn as_any(&self) -> &Any {
+        self
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
     fn is_statement(&self) -> bool {
         false
     }
@@ -487,4 +1150,11 @@ impl AstNode for NameExprNode {
         w.write_str("}")?;
         Ok(())
     }
+    fn write_source(&self, p: &mut PrettyPrinter) -> Result<(), fmt::Error> {
+        p.string(&token_text(&self.name));
+        Ok(())
+    }
+}
+impl AstNodeKind for NameExprNode {
+    const KIND: AstKind = AstKind::NameExpr;
 }
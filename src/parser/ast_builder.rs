@@ -0,0 +1,315 @@
+
+use std::fmt;
+
+use parser::ast::*;
+use parser::input_stream::VecInputStream;
+use parser::tokenizer::{self, Token};
+
+/// A token together with the exact source text it was scanned from,
+/// carried on AST nodes that need to re-emit or inspect the original
+/// spelling (identifier names, operators, `var` binding names).
+#[derive(Debug, Clone)]
+pub struct FullToken {
+    kind: Token,
+    text: String
+}
+impl FullToken {
+    pub fn new(kind: Token, text: String) -> FullToken {
+        FullToken { kind, text }
+    }
+
+    pub fn kind(&self) -> Token {
+        self.kind
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn write_token(&self, w: &mut fmt::Write) -> Result<(), fmt::Error> {
+        w.write_str(&self.text)
+    }
+}
+
+struct Lookahead {
+    token: FullToken,
+    start: usize,
+    end: usize
+}
+
+/// A hand-written recursive-descent parser over a `VecInputStream`,
+/// producing the trait-object AST in `parser::ast`. Every `parse_*`
+/// production records the stream offset before it consumes its first
+/// token and after it consumes its last, so the node it builds carries
+/// an accurate `Span`.
+pub struct AstBuilder {
+    stream: VecInputStream,
+    lookahead: Option<Lookahead>,
+    prev_end: usize
+}
+impl AstBuilder {
+    pub fn new(stream: VecInputStream) -> AstBuilder {
+        let mut builder = AstBuilder { stream, lookahead: None, prev_end: 0 };
+        builder.lookahead = builder.scan();
+        builder
+    }
+
+    fn scan(&mut self) -> Option<Lookahead> {
+        let start = self.stream.offset();
+        let (kind, text) = tokenizer::scan_token(&mut self.stream)?;
+        let end = self.stream.offset();
+        Some(Lookahead { token: FullToken::new(kind, text), start, end })
+    }
+
+    fn peek(&self) -> Token {
+        self.lookahead.as_ref().map_or(Token::Eof, |lookahead| lookahead.token.kind())
+    }
+    fn start_offset(&self) -> usize {
+        self.lookahead.as_ref().map_or(self.prev_end, |lookahead| lookahead.start)
+    }
+    fn bump(&mut self) -> FullToken {
+        let lookahead = self.lookahead.take().expect("bump() called at end of input");
+        self.prev_end = lookahead.end;
+        self.lookahead = self.scan();
+        lookahead.token
+    }
+    fn expect(&mut self, kind: Token) -> Result<FullToken, String> {
+        if self.peek() == kind {
+            Ok(self.bump())
+        } else {
+            Err(format!("expected {:?}, found {:?}", kind, self.peek()))
+        }
+    }
+
+    pub fn parse_program(&mut self) -> Result<Box<ProgramNode>, String> {
+        let start = self.start_offset();
+        let mut source_elements = Vec::new();
+        while self.lookahead.is_some() {
+            source_elements.push(self.parse_source_element()?);
+        }
+        let mut program = ProgramNode::new(Span::new(start, self.prev_end));
+        for source_element in source_elements {
+            program.add_source_element(source_element);
+        }
+        Ok(Box::new(program))
+    }
+
+    fn parse_source_element(&mut self) -> Result<Box<AstNode>, String> {
+        self.parse_statement()
+    }
+
+    fn parse_statement(&mut self) -> Result<Box<AstNode>, String> {
+        match self.peek() {
+            Token::Var => self.parse_var_stmt(),
+            Token::Semicolon => self.parse_empty_stmt(),
+            Token::If => self.parse_if_stmt(),
+            Token::While => self.parse_while_stmt(),
+            Token::For => self.parse_for_stmt(),
+            Token::Function => self.parse_function_decl(),
+            Token::Return => self.parse_return_stmt(),
+            Token::LeftBrace => self.parse_block(),
+            _ => self.parse_expr_stmt()
+        }
+    }
+
+    fn parse_var_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::Var)?;
+        let mut names = Vec::with_capacity(1);
+        loop {
+            names.push(self.expect(Token::Identifier)?);
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.bump();
+        }
+        self.expect(Token::Semicolon)?;
+        let mut node = VarStmtNode::new(Span::new(start, self.prev_end));
+        for name in names {
+            node.add_variable(name);
+        }
+        Ok(Box::new(node))
+    }
+
+    fn parse_empty_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::Semicolon)?;
+        Ok(Box::new(EmptyStmtNode::new(Span::new(start, self.prev_end))))
+    }
+
+    fn parse_if_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::If)?;
+        self.expect(Token::LeftParen)?;
+        let cond_expr = self.parse_expression()?;
+        self.expect(Token::RightParen)?;
+        let if_true_stmt = self.parse_statement()?;
+        if self.peek() == Token::Else {
+            self.bump();
+            let else_stmt = self.parse_statement()?;
+            return Ok(Box::new(IfStmtNode::new_if_else(
+                Span::new(start, self.prev_end), cond_expr, if_true_stmt, else_stmt)));
+        }
+        Ok(Box::new(IfStmtNode::new_if(Span::new(start, self.prev_end), cond_expr, if_true_stmt)))
+    }
+
+    fn parse_while_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::While)?;
+        self.expect(Token::LeftParen)?;
+        let cond_expr = self.parse_expression()?;
+        self.expect(Token::RightParen)?;
+        let body = self.parse_statement()?;
+        Ok(Box::new(WhileStmtNode::new(Span::new(start, self.prev_end), cond_expr, body)))
+    }
+
+    fn parse_for_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::For)?;
+        self.expect(Token::LeftParen)?;
+        // `setup` and the trailing ";" it owns are parsed together, by
+        // the same productions a bare var/expression statement would use
+        // -- `ForStmtNode` stores it as a statement for exactly this
+        // reason (see `ast::ForStmtNode::write_source`).
+        let setup = match self.peek() {
+            Token::Semicolon => { self.bump(); None }
+            Token::Var => Some(self.parse_var_stmt()?),
+            _ => Some(self.parse_expr_stmt()?)
+        };
+        let condition = if self.peek() == Token::Semicolon {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect(Token::Semicolon)?;
+        let exec = if self.peek() == Token::RightParen {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect(Token::RightParen)?;
+        let body = self.parse_statement()?;
+        Ok(Box::new(ForStmtNode::new(Span::new(start, self.prev_end), setup, condition, exec, body)))
+    }
+
+    fn parse_function_decl(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::Function)?;
+        let name = self.expect(Token::Identifier)?;
+        self.expect(Token::LeftParen)?;
+        let mut params = Vec::with_capacity(2);
+        if self.peek() != Token::RightParen {
+            loop {
+                params.push(self.expect(Token::Identifier)?);
+                if self.peek() != Token::Comma {
+                    break;
+                }
+                self.bump();
+            }
+        }
+        self.expect(Token::RightParen)?;
+        let body = self.parse_block()?;
+        let mut node = FunctionDeclNode::new(Span::new(start, self.prev_end), name, body);
+        for param in params {
+            node.add_param(param);
+        }
+        Ok(Box::new(node))
+    }
+
+    fn parse_return_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::Return)?;
+        let expr = if self.peek() == Token::Semicolon {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect(Token::Semicolon)?;
+        Ok(Box::new(ReturnStmtNode::new(Span::new(start, self.prev_end), expr)))
+    }
+
+    fn parse_block(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        self.expect(Token::LeftBrace)?;
+        let mut statements = Vec::new();
+        while self.peek() != Token::RightBrace {
+            statements.push(self.parse_statement()?);
+        }
+        self.expect(Token::RightBrace)?;
+        let mut block = BlockStmtNode::new(Span::new(start, self.prev_end));
+        for statement in statements {
+            block.add_statement(statement);
+        }
+        Ok(Box::new(block))
+    }
+
+    fn parse_expr_stmt(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        let expr = self.parse_expression()?;
+        self.expect(Token::Semicolon)?;
+        Ok(Box::new(ExprStmtNode::new(Span::new(start, self.prev_end), expr)))
+    }
+
+    fn parse_expression(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        let mut expr = self.parse_assignment()?;
+        while self.peek() == Token::Comma {
+            self.bump();
+            let right_expr = self.parse_assignment()?;
+            expr = Box::new(CommaExprNode::new(Span::new(start, self.prev_end), expr, right_expr));
+        }
+        Ok(expr)
+    }
+
+    fn parse_assignment(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        let left_expr = self.parse_conditional()?;
+        if self.peek().is_assignment_op() {
+            let op = self.bump();
+            let right_expr = self.parse_assignment()?;
+            return Ok(Box::new(
+                AssignExprNode::new(Span::new(start, self.prev_end), op, left_expr, right_expr)));
+        }
+        Ok(left_expr)
+    }
+
+    fn parse_conditional(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        let cond_expr = self.parse_binary()?;
+        if self.peek() == Token::Question {
+            self.bump();
+            let if_expr = self.parse_assignment()?;
+            self.expect(Token::Colon)?;
+            let else_expr = self.parse_assignment()?;
+            return Ok(Box::new(
+                CondExprNode::new(Span::new(start, self.prev_end), cond_expr, if_expr, else_expr)));
+        }
+        Ok(cond_expr)
+    }
+
+    fn parse_binary(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        let mut expr = self.parse_primary()?;
+        while self.peek().is_binary_op() {
+            let op = self.bump();
+            let right_expr = self.parse_primary()?;
+            expr = Box::new(BinaryOpExprNode::new(Span::new(start, self.prev_end), op, expr, right_expr));
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Box<AstNode>, String> {
+        let start = self.start_offset();
+        match self.peek() {
+            Token::Identifier => {
+                let name = self.bump();
+                Ok(Box::new(NameExprNode::new(Span::new(start, self.prev_end), name)))
+            }
+            Token::LeftParen => {
+                self.bump();
+                let expr = self.parse_expression()?;
+                self.expect(Token::RightParen)?;
+                Ok(expr)
+            }
+            other => Err(format!("unexpected token {:?}", other))
+        }
+    }
+}
@@ -0,0 +1,13 @@
+
+use parser::ast::{AstNode, AstNodeKind};
+
+/// Attempts to downcast `node` to the concrete node type `T`, using
+/// `node.kind()` against `T::KIND` to guard the downcast so this never
+/// panics on a mismatched variant (rust-analyzer's `ast::cast`).
+pub fn cast<T: AstNodeKind>(node: &AstNode) -> Option<&T> {
+    if node.kind() == T::KIND {
+        node.as_any().downcast_ref::<T>()
+    } else {
+        None
+    }
+}
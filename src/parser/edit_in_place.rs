@@ -0,0 +1,34 @@
+
+use parser::ast::{AstNode, ProgramNode};
+
+/// A node whose children are stored as an indexed list rather than in a
+/// handful of individually-named fields -- `ProgramNode` (through
+/// `replace_source_element`) is the only implementor today, but
+/// `replace_child` generalizes over whatever implements this instead of
+/// hard-coding `ProgramNode`.
+pub trait IndexedChildren: AstNode {
+    fn replace_child(&mut self, index: usize, replacement: Box<AstNode>) -> Box<AstNode>;
+}
+impl IndexedChildren for ProgramNode {
+    fn replace_child(&mut self, index: usize, replacement: Box<AstNode>) -> Box<AstNode> {
+        self.replace_source_element(index, replacement)
+    }
+}
+
+/// A splice helper for rewriting passes, inspired by rust-analyzer's
+/// `edit_in_place`. Replaces the child at `position` in `node` with
+/// `replacement` and returns the node that occupied that position
+/// before, so constant folding, `&&`/`?:` simplification, and similar
+/// passes can graft in nodes built with [`make`](../make/index.html)
+/// without hand-rolling the splice.
+///
+/// Node types whose children are individually-named fields are edited
+/// directly through their own setters where they have them
+/// (`IfStmtNode::set_cond_expr`, `BinaryOpExprNode::set_left_expr`,
+/// etc.) instead -- there's no indexed position to generalize over for
+/// them.
+pub fn replace_child<T: IndexedChildren>(node: &mut T, position: usize, replacement: Box<AstNode>)
+    -> Box<AstNode>
+{
+    node.replace_child(position, replacement)
+}
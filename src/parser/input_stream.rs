@@ -0,0 +1,42 @@
+
+/// A `Vec<u8>`-backed input stream with a cursor. The tokenizer scans
+/// source bytes off of it, and `AstBuilder` reads its current `offset()`
+/// before and after scanning each token, from which it derives node
+/// `Span`s.
+pub struct VecInputStream {
+    buf: Vec<u8>,
+    pos: usize
+}
+impl VecInputStream {
+    pub fn new(buf: Vec<u8>) -> VecInputStream {
+        VecInputStream { buf, pos: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        self.buf.get(self.pos).cloned()
+    }
+    pub fn advance(&mut self) -> Option<u8> {
+        let byte = self.peek();
+        if byte.is_some() {
+            self.pos += 1;
+        }
+        byte
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> &[u8] {
+        &self.buf[start..end]
+    }
+}
@@ -0,0 +1,74 @@
+
+use parser::ast::*;
+use parser::ast_builder::FullToken;
+use parser::tokenizer::Token;
+
+/// Ergonomic constructors for synthesizing `AstNode`s without running a
+/// real parse, modeled on rust-analyzer's `ast::make`. Each constructor
+/// fabricates the `FullToken`s that a hand-written `AstBuilder::parse_*`
+/// production would otherwise have produced, so callers building
+/// source-to-source transforms, desugaring passes, or test fixtures don't
+/// have to drive the tokenizer by hand.
+///
+/// Nodes built here carry a zero-length `Span` at offset 0, since they
+/// don't correspond to any real range of source text.
+fn synthetic_span() -> Span {
+    Span::new(0, 0)
+}
+
+pub fn name_expr(name: &str) -> Box<AstNode> {
+    let token = FullToken::new(Token::Identifier, name.to_string());
+    Box::new(NameExprNode::new(synthetic_span(), token))
+}
+
+pub fn binary_op(op: Token, op_text: &str, left_expr: Box<AstNode>, right_expr: Box<AstNode>)
+    -> Box<AstNode>
+{
+    let token = FullToken::new(op, op_text.to_string());
+    Box::new(BinaryOpExprNode::new(synthetic_span(), token, left_expr, right_expr))
+}
+
+pub fn assign_expr(op: Token, op_text: &str, left_expr: Box<AstNode>, right_expr: Box<AstNode>)
+    -> Box<AstNode>
+{
+    let token = FullToken::new(op, op_text.to_string());
+    Box::new(AssignExprNode::new(synthetic_span(), token, left_expr, right_expr))
+}
+
+pub fn cond_expr(cond_expr: Box<AstNode>, if_expr: Box<AstNode>, else_expr: Box<AstNode>)
+    -> Box<AstNode>
+{
+    Box::new(CondExprNode::new(synthetic_span(), cond_expr, if_expr, else_expr))
+}
+
+pub fn comma_expr(left_expr: Box<AstNode>, right_expr: Box<AstNode>) -> Box<AstNode> {
+    Box::new(CommaExprNode::new(synthetic_span(), left_expr, right_expr))
+}
+
+pub fn expr_stmt(expr: Box<AstNode>) -> Box<AstNode> {
+    Box::new(ExprStmtNode::new(synthetic_span(), expr))
+}
+
+pub fn empty_stmt() -> Box<AstNode> {
+    Box::new(EmptyStmtNode::new(synthetic_span()))
+}
+
+pub fn block(statements: Vec<Box<AstNode>>) -> Box<AstNode> {
+    let mut node = BlockStmtNode::new(synthetic_span());
+    for statement in statements {
+        node.add_statement(statement);
+    }
+    Box::new(node)
+}
+
+pub fn if_stmt(cond_expr: Box<AstNode>, body: Box<AstNode>) -> Box<AstNode> {
+    Box::new(IfStmtNode::new_if(synthetic_span(), cond_expr, body))
+}
+
+pub fn program(source_elements: Vec<Box<AstNode>>) -> Box<AstNode> {
+    let mut node = ProgramNode::new(synthetic_span());
+    for source_element in source_elements {
+        node.add_source_element(source_element);
+    }
+    Box::new(node)
+}
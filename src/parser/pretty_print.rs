@@ -0,0 +1,275 @@
+
+/// Whether the breaks inside a logical box are printed "consistently" --
+/// every break in the group becomes a newline once the group doesn't fit
+/// on the line -- or "inconsistently", where each break independently
+/// prints as a space as long as the text up to the *next* break still
+/// fits. Consistent breaking suits statement lists; inconsistent breaking
+/// suits call-argument-style lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breaks {
+    Consistent,
+    Inconsistent
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BeginToken {
+    offset: isize,
+    breaks: Breaks
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BreakToken {
+    offset: isize,
+    blank_space: usize,
+    // Forces `should_break` regardless of whether the enclosing group
+    // fits -- for separators (top-level statements, block statements)
+    // that must never collapse onto one line. See `PrettyPrinter::hardbreak`.
+    hard: bool
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Str(String),
+    Break(BreakToken),
+    Begin(BeginToken),
+    End
+}
+
+/// The printed size of a buffered token. A box's size is `Infinity`
+/// while its matching `End` hasn't been scanned yet, and a break's while
+/// neither the next `Break` nor the `End` of its group has been -- only
+/// then do we know how wide the text it governs actually is.
+#[derive(Debug, Clone, Copy)]
+enum Size {
+    Known(isize),
+    Infinity
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PrintFrame {
+    // The indentation in effect when this box was entered -- restored
+    // on `End` and used (together with `offset`) as the base for any
+    // break inside this box, instead of the breaks compounding onto
+    // whatever `self.indent` happened to already be.
+    base: isize,
+    offset: isize,
+    breaks: Breaks
+}
+
+/// An Oppen-style two-phase pretty printer, in the style of rustc's
+/// `pprust`. Callers feed it `string`, `brk` and `open_box`/`close_box`
+/// operations describing the logical structure of the source being
+/// reconstructed; a `scan` phase buffers tokens until it can compute the
+/// total size of each logical group, and a `print` phase then decides,
+/// per box, whether the group fits in the remaining `max_width` columns --
+/// printing each of its breaks as its `blank_space` spaces if it does, or,
+/// if it doesn't, as a newline plus indentation at every break of a
+/// `Breaks::Consistent` box and only at those breaks of a
+/// `Breaks::Inconsistent` box whose following text no longer fits.
+///
+/// `AstNode::write_source` drives this to re-emit syntactically valid,
+/// indented JavaScript instead of `write_tree`'s debug dump.
+pub struct PrettyPrinter {
+    margin: usize,
+    max_width: usize,
+    out: String,
+
+    // Tokens not yet resolved by the scan phase, in push order, held in a
+    // growable `Vec` with no size bound (printed tokens are removed from
+    // its front), and the stack of indices of their still-open
+    // `Begin`/`Break` tokens.
+    // `lefts` runs in lockstep with `scan_stack`, recording `right_total`
+    // at the moment each entry was pushed so its eventual size can be
+    // read off as `right_total - left` once it closes.
+    buf: Vec<(Token, Size)>,
+    scan_stack: Vec<usize>,
+    lefts: Vec<isize>,
+    right_total: isize,
+
+    print_stack: Vec<PrintFrame>,
+    space: isize,
+    indent: usize
+}
+impl PrettyPrinter {
+    pub fn new(margin: usize, max_width: usize) -> PrettyPrinter {
+        PrettyPrinter {
+            margin,
+            max_width,
+            out: String::new(),
+            buf: Vec::new(),
+            scan_stack: Vec::new(),
+            lefts: Vec::new(),
+            right_total: 0,
+            print_stack: Vec::new(),
+            space: max_width as isize,
+            indent: 0
+        }
+    }
+
+    pub fn margin(&self) -> usize {
+        self.margin
+    }
+    pub fn max_width(&self) -> usize {
+        self.max_width
+    }
+
+    /// Opens a logical group, indented by `offset` columns relative to
+    /// the enclosing one whenever its breaks end up printing as
+    /// newlines. Breaks inside the group print consistently or
+    /// inconsistently depending on whether the whole group fits.
+    pub fn open_box(&mut self, offset: isize, breaks: Breaks) {
+        self.scan_push(Token::Begin(BeginToken { offset, breaks }));
+    }
+    pub fn close_box(&mut self) {
+        self.scan_push(Token::End);
+    }
+
+    /// Emits literal, unbreakable text.
+    pub fn string(&mut self, text: &str) {
+        self.scan_push(Token::Str(text.to_string()));
+    }
+
+    /// Emits a potential line break: `blank_space` spaces, or, where the
+    /// enclosing group's `Breaks` mode calls for a break, a newline
+    /// indented by `offset` columns beyond the group's own indentation
+    /// (the indentation in effect when it was opened plus its `offset`).
+    pub fn brk(&mut self, offset: isize, blank_space: usize) {
+        self.scan_push(Token::Break(BreakToken { offset, blank_space, hard: false }));
+    }
+
+    /// Emits an unconditional newline: unlike `brk`, this prints as a
+    /// newline even when the enclosing group fits on the line, for
+    /// separators (top-level statements, block statements) that must
+    /// never collapse onto one line.
+    pub fn hardbreak(&mut self) {
+        self.scan_push(Token::Break(BreakToken { offset: 0, blank_space: 0, hard: true }));
+    }
+
+    /// Runs the print phase over any tokens still buffered and returns
+    /// the reconstructed source text.
+    pub fn finish(mut self) -> String {
+        while !self.buf.is_empty() {
+            let (token, size) = self.buf.remove(0);
+            self.print(token, size);
+        }
+        self.out
+    }
+
+    fn scan_push(&mut self, token: Token) {
+        match token {
+            Token::Begin(_) => {
+                self.scan_stack.push(self.buf.len());
+                self.lefts.push(self.right_total);
+                self.buf.push((token, Size::Infinity));
+            }
+            Token::End => {
+                self.buf.push((token, Size::Known(0)));
+                self.resolve_trailing_break();
+                self.resolve_top();
+            }
+            Token::Break(break_token) => {
+                self.resolve_trailing_break();
+                self.scan_stack.push(self.buf.len());
+                self.lefts.push(self.right_total);
+                self.right_total += break_token.blank_space as isize;
+                self.buf.push((token, Size::Infinity));
+            }
+            Token::Str(ref text) => {
+                let len = text.len() as isize;
+                self.right_total += len;
+                self.buf.push((token.clone(), Size::Known(len)));
+            }
+        }
+        // The scan phase only needs to hold onto tokens whose group size
+        // is still unresolved; flush everything else through to print.
+        while self.scan_stack.is_empty() && !self.buf.is_empty() {
+            let (token, size) = self.buf.remove(0);
+            self.print(token, size);
+        }
+    }
+
+    /// Resolves the break on top of the scan stack, if there is one --
+    /// called before pushing a new `Break` or `End` so a group never
+    /// accumulates more than one *unresolved* trailing break. Leaves the
+    /// stack alone when the top entry is a `Begin`, since that one isn't
+    /// closing yet.
+    fn resolve_trailing_break(&mut self) {
+        if let Some(&index) = self.scan_stack.last() {
+            if let Token::Break(_) = self.buf[index].0 {
+                self.scan_stack.pop();
+                let left = self.lefts.pop().unwrap();
+                self.buf[index].1 = Size::Known(self.right_total - left);
+            }
+        }
+    }
+
+    /// Resolves whatever's on top of the scan stack -- the matching
+    /// `Begin` for an `End`, once any trailing break it contains has
+    /// already been resolved. Its size is the real Oppen right-total
+    /// delta (the sum of every `Str` width and realized `Break` blank
+    /// space inside it), not a count of buffered tokens.
+    fn resolve_top(&mut self) {
+        if let Some(index) = self.scan_stack.pop() {
+            let left = self.lefts.pop().unwrap();
+            self.buf[index].1 = Size::Known(self.right_total - left);
+        }
+    }
+
+    fn print(&mut self, token: Token, size: Size) {
+        match token {
+            Token::Begin(begin_token) => {
+                let breaks = if self.fits(size) { Breaks::Inconsistent } else { begin_token.breaks };
+                self.print_stack.push(PrintFrame {
+                    base: self.indent as isize,
+                    offset: begin_token.offset,
+                    breaks
+                });
+            }
+            Token::End => {
+                if let Some(frame) = self.print_stack.pop() {
+                    self.indent = frame.base as usize;
+                }
+            }
+            Token::Break(break_token) => {
+                let frame = self.print_stack.last().cloned().unwrap_or(PrintFrame {
+                    base: 0,
+                    offset: 0,
+                    breaks: Breaks::Inconsistent
+                });
+                let should_break = break_token.hard || match frame.breaks {
+                    Breaks::Consistent => true,
+                    Breaks::Inconsistent => !self.fits(size)
+                };
+                if should_break {
+                    let indent = frame.base + frame.offset + break_token.offset;
+                    self.indent = if indent > 0 { indent as usize } else { 0 };
+                    self.newline();
+                } else {
+                    for _ in 0..break_token.blank_space {
+                        self.out.push(' ');
+                    }
+                    self.space -= break_token.blank_space as isize;
+                }
+            }
+            Token::Str(text) => {
+                self.space -= text.len() as isize;
+                self.out.push_str(&text);
+            }
+        }
+    }
+
+    fn fits(&self, size: Size) -> bool {
+        match size {
+            Size::Known(n) => n <= self.space,
+            Size::Infinity => false
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push(' ');
+        }
+        self.space = self.max_width as isize - self.indent as isize;
+    }
+}
@@ -0,0 +1,110 @@
+
+use parser::input_stream::VecInputStream;
+
+/// The lexical category of a token. The tokenizer only recognizes as
+/// much punctuation and as many keywords as the grammar subset that
+/// `AstBuilder`'s `parse_*` productions currently consume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Identifier,
+
+    Var,
+    If,
+    Else,
+    While,
+    For,
+    Function,
+    Return,
+
+    Semicolon,
+    Comma,
+    Question,
+    Colon,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+
+    Eof
+}
+impl Token {
+    pub fn is_identifier(&self) -> bool {
+        *self == Token::Identifier
+    }
+    pub fn is_assignment_op(&self) -> bool {
+        *self == Token::Assign
+    }
+    pub fn is_binary_op(&self) -> bool {
+        match *self {
+            Token::Plus | Token::Minus | Token::Star | Token::Slash => true,
+            _ => false
+        }
+    }
+}
+
+fn is_ident_start(byte: u8) -> bool {
+    byte == b'_' || byte == b'$' || (byte as char).is_alphabetic()
+}
+fn is_ident_continue(byte: u8) -> bool {
+    is_ident_start(byte) || byte.is_ascii_digit()
+}
+
+fn keyword(text: &str) -> Option<Token> {
+    match text {
+        "var" => Some(Token::Var),
+        "if" => Some(Token::If),
+        "else" => Some(Token::Else),
+        "while" => Some(Token::While),
+        "for" => Some(Token::For),
+        "function" => Some(Token::Function),
+        "return" => Some(Token::Return),
+        _ => None
+    }
+}
+
+/// Skips leading whitespace, then scans a single token starting at the
+/// stream's current position. Returns `None` once the stream is
+/// exhausted.
+pub fn scan_token(stream: &mut VecInputStream) -> Option<(Token, String)> {
+    while stream.peek().map_or(false, |byte| (byte as char).is_whitespace()) {
+        stream.advance();
+    }
+    let byte = stream.peek()?;
+
+    if is_ident_start(byte) {
+        let start = stream.offset();
+        while stream.peek().map_or(false, is_ident_continue) {
+            stream.advance();
+        }
+        let text = String::from_utf8_lossy(stream.slice(start, stream.offset())).into_owned();
+        let kind = keyword(&text).unwrap_or(Token::Identifier);
+        return Some((kind, text));
+    }
+
+    stream.advance();
+    // FIXME: an unrecognized byte is silently treated as Eof rather than
+    // reported as a lexical error -- there's no diagnostics machinery yet.
+    let kind = match byte {
+        b';' => Token::Semicolon,
+        b',' => Token::Comma,
+        b'?' => Token::Question,
+        b':' => Token::Colon,
+        b'(' => Token::LeftParen,
+        b')' => Token::RightParen,
+        b'{' => Token::LeftBrace,
+        b'}' => Token::RightBrace,
+        b'=' => Token::Assign,
+        b'+' => Token::Plus,
+        b'-' => Token::Minus,
+        b'*' => Token::Star,
+        b'/' => Token::Slash,
+        _ => Token::Eof
+    };
+    Some((kind, (byte as char).to_string()))
+}
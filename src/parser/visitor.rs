@@ -0,0 +1,99 @@
+
+use parser::ast::*;
+use parser::cast::cast;
+
+/// A visitor over the trait-object AST, with one `visit_*` method per
+/// `AstKind`. Every method is default-implemented to recurse into the
+/// node's children, so a pass like name resolution or constant folding
+/// only needs to override the productions it cares about.
+pub trait Visitor {
+    fn visit_program(&mut self, node: &ProgramNode) {
+        for source_element in node.source_elements() {
+            walk(source_element.as_ref(), self);
+        }
+    }
+    fn visit_block_stmt(&mut self, node: &BlockStmtNode) {
+        for statement in node.statements() {
+            walk(statement.as_ref(), self);
+        }
+    }
+    fn visit_var_stmt(&mut self, _node: &VarStmtNode) {
+    }
+    fn visit_empty_stmt(&mut self, _node: &EmptyStmtNode) {
+    }
+    fn visit_if_stmt(&mut self, node: &IfStmtNode) {
+        walk(node.cond_expr(), self);
+        walk(node.if_true_stmt(), self);
+        if let Some(else_stmt) = node.else_stmt() {
+            walk(else_stmt, self);
+        }
+    }
+    fn visit_while_stmt(&mut self, node: &WhileStmtNode) {
+        walk(node.cond_expr(), self);
+        walk(node.body(), self);
+    }
+    fn visit_for_stmt(&mut self, node: &ForStmtNode) {
+        if let Some(setup) = node.setup() {
+            walk(setup, self);
+        }
+        if let Some(condition) = node.condition() {
+            walk(condition, self);
+        }
+        if let Some(exec) = node.exec() {
+            walk(exec, self);
+        }
+        walk(node.body(), self);
+    }
+    fn visit_fn_stmt(&mut self, node: &FunctionDeclNode) {
+        walk(node.body(), self);
+    }
+    fn visit_return_stmt(&mut self, node: &ReturnStmtNode) {
+        if let Some(expr) = node.expression() {
+            walk(expr, self);
+        }
+    }
+    fn visit_expr_stmt(&mut self, node: &ExprStmtNode) {
+        walk(node.expression(), self);
+    }
+    fn visit_binary_op_expr(&mut self, node: &BinaryOpExprNode) {
+        walk(node.left_expr(), self);
+        walk(node.right_expr(), self);
+    }
+    fn visit_cond_expr(&mut self, node: &CondExprNode) {
+        walk(node.cond_expr(), self);
+        walk(node.if_expr(), self);
+        walk(node.else_expr(), self);
+    }
+    fn visit_assign_expr(&mut self, node: &AssignExprNode) {
+        walk(node.left_expr(), self);
+        walk(node.right_expr(), self);
+    }
+    fn visit_comma_expr(&mut self, node: &CommaExprNode) {
+        walk(node.left_expr(), self);
+        walk(node.right_expr(), self);
+    }
+    fn visit_name_expr(&mut self, _node: &NameExprNode) {
+    }
+}
+
+/// Dispatches `node` to the matching `visit_*` method on `visitor`,
+/// downcasting via [`cast`](../cast/fn.cast.html) based on `node.kind()`.
+pub fn walk<V: Visitor + ?Sized>(node: &AstNode, visitor: &mut V) {
+    match node.kind() {
+        AstKind::Program => visitor.visit_program(cast(node).unwrap()),
+        AstKind::BlockStmt => visitor.visit_block_stmt(cast(node).unwrap()),
+        AstKind::VarStmt => visitor.visit_var_stmt(cast(node).unwrap()),
+        AstKind::EmptyStmt => visitor.visit_empty_stmt(cast(node).unwrap()),
+        AstKind::IfStmt => visitor.visit_if_stmt(cast(node).unwrap()),
+        AstKind::WhileStmt => visitor.visit_while_stmt(cast(node).unwrap()),
+        AstKind::ForStmt => visitor.visit_for_stmt(cast(node).unwrap()),
+        AstKind::FnStmt => visitor.visit_fn_stmt(cast(node).unwrap()),
+        AstKind::ReturnStmt => visitor.visit_return_stmt(cast(node).unwrap()),
+        AstKind::ExprStmt => visitor.visit_expr_stmt(cast(node).unwrap()),
+        AstKind::BinaryOpExpr => visitor.visit_binary_op_expr(cast(node).unwrap()),
+        AstKind::CondExpr => visitor.visit_cond_expr(cast(node).unwrap()),
+        AstKind::AssignExpr => visitor.visit_assign_expr(cast(node).unwrap()),
+        AstKind::CommaExpr => visitor.visit_comma_expr(cast(node).unwrap()),
+        AstKind::NameExpr => visitor.visit_name_expr(cast(node).unwrap())
+    }
+}